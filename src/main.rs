@@ -8,7 +8,7 @@ use ratatui::{
     prelude::*,
     widgets::{Block, Borders, Cell, Paragraph, Row, Table},
 };
-use std::{collections::{HashMap, HashSet}, io};
+use std::{cell::RefCell, collections::{HashMap, HashSet}, io};
 
 // --- 1. Physics Engine v5: Lightweight & Robust ---
 
@@ -33,9 +33,182 @@ fn idx_to_note_name(idx: u8) -> &'static str {
     }
 }
 
+// --- 1a'. Note Model: 文字(A-G)+変化記号でスペリングを正しくする ---
+
+// レター: 0=C,1=D,2=E,3=F,4=G,5=A,6=B。accidental は -2..=+2 (♭♭..♯♯)。
+#[derive(Clone, Copy)]
+struct Note {
+    letter: u8,
+    accidental: i8,
+}
+
+// レター単体のピッチクラス (ナチュラル時)
+fn letter_pc(letter: u8) -> u8 {
+    [0, 2, 4, 5, 7, 9, 11][(letter % 7) as usize]
+}
+
+impl Note {
+    fn pc(&self) -> u8 {
+        ((letter_pc(self.letter) as i16 + self.accidental as i16).rem_euclid(12)) as u8
+    }
+
+    fn name(&self) -> String {
+        let base = ['C', 'D', 'E', 'F', 'G', 'A', 'B'][(self.letter % 7) as usize];
+        let acc = match self.accidental {
+            a if a > 0 => "#".repeat(a as usize),
+            a if a < 0 => "b".repeat((-a) as usize),
+            _ => String::new(),
+        };
+        format!("{}{}", base, acc)
+    }
+}
+
+// target_pc を、指定レターのナチュラル音からの符号付き差 (-? ..=?) で表す
+fn signed_offset(target_pc: u8, nat_pc: u8) -> i8 {
+    let d = ((target_pc as i16 + 12 - nat_pc as i16) % 12) as i8;
+    if d > 6 {
+        d - 12
+    } else {
+        d
+    }
+}
+
+// 調の主音 (ピッチクラス) の慣用スペリング。♭系を基本に、#記号は F# のみ。
+fn default_tonic_note(pc: u8) -> Note {
+    let (letter, acc) = match pc % 12 {
+        0 => (0, 0),   // C
+        1 => (1, -1),  // Db
+        2 => (1, 0),   // D
+        3 => (2, -1),  // Eb
+        4 => (2, 0),   // E
+        5 => (3, 0),   // F
+        6 => (3, 1),   // F#
+        7 => (4, 0),   // G
+        8 => (5, -1),  // Ab
+        9 => (5, 0),   // A
+        10 => (6, -1), // Bb
+        _ => (6, 0),   // B
+    };
+    Note { letter, accidental: acc }
+}
+
+// 連続するレターを1つずつ使って音階をスペリングする (各レター1回ずつ)。
+fn spell_scale(tonic: Note, intervals: &[u8]) -> Vec<Note> {
+    let tonic_pc = tonic.pc();
+    intervals
+        .iter()
+        .enumerate()
+        .map(|(i, &iv)| {
+            let letter = (tonic.letter + i as u8) % 7;
+            let target = (tonic_pc + iv) % 12;
+            Note {
+                letter,
+                accidental: signed_offset(target, letter_pc(letter)),
+            }
+        })
+        .collect()
+}
+
+// 調に応じた 12 音のスペリング表。ダイアトニック音は音階の綴りを使い、
+// それ以外は慣用スペリングにフォールバックする。
+fn key_spelling(key_pc: u8) -> HashMap<u8, Note> {
+    let tonic = default_tonic_note(key_pc);
+    let scale = spell_scale(tonic, &[0, 2, 4, 5, 7, 9, 11]);
+    let mut table: HashMap<u8, Note> = HashMap::new();
+    for n in scale {
+        table.insert(n.pc(), n);
+    }
+    for pc in 0u8..12 {
+        table.entry(pc).or_insert_with(|| default_tonic_note(pc));
+    }
+    table
+}
+
+// 調を踏まえた音名。key_spelling 経由でダイアトニックな綴りを優先する。
+fn spell_pc(pc: u8, key_pc: u8) -> String {
+    key_spelling(key_pc)
+        .get(&(pc % 12))
+        .map(|n| n.name())
+        .unwrap_or_else(|| idx_to_note_name(pc).to_string())
+}
+
+// コード構成音を「機能」に従って綴る (例: C7#9 の #9 は D# であって Eb ではない)。
+// 元のインターバル値 (9度なら 14/15 など) を使って度数を判定する。
+// 半音値が両義的なもの (6, 9) は、コード全体のインターバル列 `chord` を見て
+// 度数を確定する: 完全5度 (7) があれば 6=#11・9=13、無ければ 6=b5・9=dim7。
+fn spell_chord_tone(root: Note, interval: u8, chord: &[u8]) -> Note {
+    let has_fifth = chord.contains(&7);
+    // インターバル -> レターのオフセット (3度=+2, 9度=+1 ...)
+    let offset = match interval {
+        0 => 0,
+        1 | 2 => 1,
+        3 | 4 => 2,
+        5 => 3,
+        6 => if has_fifth { 3 } else { 4 }, // #11 (完全5度あり) か b5 か
+        7 | 8 => 4,
+        9 => if has_fifth { 5 } else { 6 }, // 13/6 (完全5度あり) か dim7 か
+        10 | 11 => 6,
+        13..=15 => 1, // b9 / 9 / #9
+        17 => 3,           // 11
+        21 => 5,           // 13
+        _ => {
+            // 未知の度数はピッチクラスから慣用スペリング
+            let pc = (root.pc() + interval) % 12;
+            return default_tonic_note(pc);
+        }
+    };
+    let letter = (root.letter + offset) % 7;
+    let target = (root.pc() + interval) % 12;
+    Note {
+        letter,
+        accidental: signed_offset(target, letter_pc(letter)),
+    }
+}
+
 // コード定義辞書
 // ここに定義を追加すれば、どんな変態コードも即座に対応可能
 fn get_quality_intervals(quality: &str) -> Vec<u8> {
+    get_quality_intervals_v2(quality).0
+}
+
+// ★ ボイシング探索用: インターバルと「必須/省略可」フラグを並行して返す。
+// Root / 3rd / 7th は省略不可(required)、5th・テンション類は弦が足りなければ省略可能。
+// ukebox の required/optional interval の考え方をここに取り込む。
+fn get_quality_intervals_v2(quality: &str) -> (Vec<u8>, Vec<bool>) {
+    let intervals = get_quality_interval_list(quality);
+    let priority = interval_priority(&intervals);
+    (intervals, priority)
+}
+
+// 各インターバルが必須かどうかを判定する。
+// - 0 (Root) は常に必須
+// - 最初に現れる 3rd (3 or 4) は必須
+// - 最初に現れる 7th (10 or 11) は必須
+// それ以外(5th・6th・9th/11th/13th 等のテンション)は省略可。
+fn interval_priority(intervals: &[u8]) -> Vec<bool> {
+    let mut third_taken = false;
+    let mut seventh_taken = false;
+    intervals
+        .iter()
+        .map(|&iv| {
+            let pc = iv % 12;
+            match pc {
+                0 => true,
+                3 | 4 if !third_taken => {
+                    third_taken = true;
+                    true
+                }
+                10 | 11 if !seventh_taken => {
+                    seventh_taken = true;
+                    true
+                }
+                _ => false,
+            }
+        })
+        .collect()
+}
+
+fn get_quality_interval_list(quality: &str) -> Vec<u8> {
     match quality {
         // Basic
         "" | "M" | "maj"          => vec![0, 4, 7],
@@ -76,6 +249,24 @@ fn get_quality_intervals(quality: &str) -> Vec<u8> {
     }
 }
 
+// 記号を「ルート」と「クオリティ」に分割する。1 文字目はレター、
+// 2 文字目が # / b ならそれもルートに含める。バイト添字 (&s[0..2]) は
+// マルチバイト文字を途中で割って panic しうるので、char 境界で切る。
+fn split_root_quality(symbol: &str) -> (&str, &str) {
+    let mut indices = symbol.char_indices();
+    if indices.next().is_none() {
+        return (symbol, "");
+    }
+    match indices.next() {
+        Some((i, c)) if c == '#' || c == 'b' => {
+            let end = i + c.len_utf8();
+            (&symbol[..end], &symbol[end..])
+        }
+        Some((i, _)) => (&symbol[..i], &symbol[i..]),
+        None => (symbol, ""),
+    }
+}
+
 // オンコード対応パーサー
 // "C/Bb" -> Root: C, Bass: Bb, Notes: [C, E, G, Bb]
 fn parse_chord_v5(input: &str) -> (String, String, Vec<u8>) {
@@ -89,17 +280,7 @@ fn parse_chord_v5(input: &str) -> (String, String, Vec<u8>) {
     let bass_str = if parts.len() > 1 { parts[1] } else { "" };
 
     // 2. Root Separation
-    // 2文字目(#/b)チェック
-    let (root_str, quality_str) = if symbol.len() > 1 {
-        let second = symbol.chars().nth(1).unwrap();
-        if second == '#' || second == 'b' {
-            (&symbol[0..2], &symbol[2..])
-        } else {
-            (&symbol[0..1], &symbol[1..])
-        }
-    } else {
-        (symbol, "")
-    };
+    let (root_str, quality_str) = split_root_quality(symbol);
 
     let root_idx = match map.get(root_str) {
         Some(&i) => i,
@@ -131,14 +312,138 @@ fn parse_chord_v5(input: &str) -> (String, String, Vec<u8>) {
     (display_name, quality_str.to_string(), notes)
 }
 
-fn get_scale_mask(root_u8: u8) -> HashSet<u8> {
-    let intervals = [0, 2, 4, 5, 7, 9, 11];
-    intervals.iter().map(|i| (root_u8 + i) % 12).collect()
+// 逆引き用の正規クオリティ辞書 (別名を排した代表表記 + インターバル)。
+// get_quality_intervals の別名を全部回すと候補が重複するため、逆引きでは
+// この代表リストだけを走査する。候補の順位はスコアと名前で決まり、この並び順には依存しない。
+fn get_named_qualities() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("M13", vec![0, 4, 7, 11, 14, 21]),
+        ("13", vec![0, 4, 7, 10, 14, 21]),
+        ("m11", vec![0, 3, 7, 10, 14, 17]),
+        ("11", vec![0, 4, 7, 10, 14, 17]),
+        ("M9", vec![0, 4, 7, 11, 14]),
+        ("m9", vec![0, 3, 7, 10, 14]),
+        ("9", vec![0, 4, 7, 10, 14]),
+        ("7#9", vec![0, 4, 7, 10, 15]),
+        ("7b9", vec![0, 4, 7, 10, 13]),
+        ("add9", vec![0, 4, 7, 14]),
+        ("M7", vec![0, 4, 7, 11]),
+        ("m7", vec![0, 3, 7, 10]),
+        ("7", vec![0, 4, 7, 10]),
+        ("mM7", vec![0, 3, 7, 11]),
+        ("dim7", vec![0, 3, 6, 9]),
+        ("m7b5", vec![0, 3, 6, 10]),
+        ("7#5", vec![0, 4, 8, 10]),
+        ("6", vec![0, 4, 7, 9]),
+        ("m6", vec![0, 3, 7, 9]),
+        // メジャー三和音は空クオリティ。parse_chord_v5 が食える表記に合わせる (C であって CM ではない)。
+        ("", vec![0, 4, 7]),
+        ("m", vec![0, 3, 7]),
+        ("dim", vec![0, 3, 6]),
+        ("aug", vec![0, 4, 8]),
+        ("sus4", vec![0, 5, 7]),
+        ("sus2", vec![0, 2, 7]),
+        ("5", vec![0, 7]),
+    ]
+}
+
+// --- 1a. Reverse Identification: 音の集合からコード名を推定する ---
+
+// parse_chord_v5 の逆。任意のピッチクラス集合(直接入力、または
+// フレットした弦から導出)を受け取り、候補コード名をスコア順に返す。
+// 12 個のルートすべてで辞書を部分一致させ、(a) 辞書音の網羅率、
+// (b) 未説明音の少なさ、(c) 最低音をルートとみなす優先度 で採点する。
+// bass に最低音を渡すと、ルートがそれと異なる候補は転回形 (root/bass) で命名され、
+// 減点されるため、最低音をルートとする解釈より高得点のときだけその上位に並ぶ。
+// key_pc は局所キー。ルート/ベースの音名を調に沿って綴る (spell_pc 経由)。
+fn identify_chord(pitch_classes: &[u8], bass: Option<u8>, key_pc: u8) -> Vec<String> {
+    let input: HashSet<u8> = pitch_classes.iter().map(|&p| p % 12).collect();
+    if input.is_empty() {
+        return Vec::new();
+    }
+    let dict = get_named_qualities();
+
+    // (score, 表示名)
+    let mut scored: Vec<(f64, String)> = Vec::new();
+
+    for root in 0u8..12 {
+        for (label, ivs) in &dict {
+            let dict_pcs: HashSet<u8> = ivs.iter().map(|&i| (root + i) % 12).collect();
+            let present = input.intersection(&dict_pcs).count();
+            if present == 0 {
+                continue;
+            }
+            // (a) 網羅率: 辞書音のうち実際に鳴っている割合
+            let coverage = present as f64 / dict_pcs.len() as f64;
+            // (b) 未説明音: 入力にあるが辞書に無い音
+            let extra = input.difference(&dict_pcs).count() as f64;
+            // (c) 最低音をルートとみなすボーナス。転回形はわずかに減点。
+            let bass_bonus = match bass {
+                Some(b) if b % 12 == root => 0.15,
+                Some(_) => -0.05,
+                None => 0.0,
+            };
+            let score = coverage - 0.20 * extra + bass_bonus;
+
+            // 名前: ルートが最低音でなければスラッシュ表記にする。
+            // 音名は局所キーに沿って綴る (固定の idx_to_note_name ではなく)。
+            let root_name = spell_pc(root, key_pc);
+            let name = match bass {
+                Some(b) if b % 12 != root => {
+                    format!("{}{}/{}", root_name, label, spell_pc(b, key_pc))
+                }
+                _ => format!("{}{}", root_name, label),
+            };
+            scored.push((score, name));
+        }
+    }
+
+    // スコア降順。同点は名前で安定化。
+    scored.sort_by(|a, b| {
+        b.0.partial_cmp(&a.0)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.1.cmp(&b.1))
+    });
+    scored.dedup_by(|a, b| a.1 == b.1);
+    scored.into_iter().take(5).map(|(_, n)| n).collect()
+}
+
+// --- 1c. Scale Registry: メジャー固定をやめ、旋法/短音階を切替可能にする ---
+
+// スケールファミリを「ステップパターン(半音刻み)」で定義したレジストリ。
+// 表示名とステップ列のペアを返す。循環キーでこの並び順に切り替える。
+fn scale_families() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("Ionian", vec![2, 2, 1, 2, 2, 2, 1]),
+        ("Dorian", vec![2, 1, 2, 2, 2, 1, 2]),
+        ("Phrygian", vec![1, 2, 2, 2, 1, 2, 2]),
+        ("Lydian", vec![2, 2, 2, 1, 2, 2, 1]),
+        ("Mixolydian", vec![2, 2, 1, 2, 2, 1, 2]),
+        ("Aeolian", vec![2, 1, 2, 2, 1, 2, 2]),
+        ("Locrian", vec![1, 2, 2, 1, 2, 2, 2]),
+        ("HarmonicMinor", vec![2, 1, 2, 2, 1, 3, 1]),
+        ("MelodicMinor", vec![2, 1, 2, 2, 2, 2, 1]),
+        ("MajPentatonic", vec![2, 2, 3, 2, 3]),
+        ("MinPentatonic", vec![3, 2, 2, 3, 2]),
+        ("Blues", vec![3, 2, 1, 1, 3, 2]),
+    ]
+}
+
+// ステップ列からルート基準のスケール音集合 (ピッチクラス) を構築する。
+fn scale_mask_from_steps(root_u8: u8, steps: &[u8]) -> HashSet<u8> {
+    let mut set = HashSet::new();
+    set.insert(root_u8 % 12);
+    let mut acc = 0u8;
+    for &st in steps {
+        acc += st;
+        set.insert((root_u8 + acc) % 12);
+    }
+    set
 }
 
 // --- 1. Physics Engine Logic ---
 
-fn calculate_tonal_depth(chord_notes: &[u8]) -> (Vec<(i32, &'static str)>, usize, bool) {
+fn calculate_tonal_depth(chord_notes: &[u8], scale_steps: &[u8]) -> (Vec<(i32, &'static str)>, usize, bool) {
     let search_order = [
         (0, 0, "C"), (1, 7, "G"), (-1, 5, "F"),
         (2, 2, "D"), (-2, 10, "Bb"),
@@ -155,7 +460,7 @@ fn calculate_tonal_depth(chord_notes: &[u8]) -> (Vec<(i32, &'static str)>, usize
     let mut candidates: Vec<(i32, &'static str)> = Vec::new();
 
     for (depth, r_idx, r_name) in search_order {
-        let scale = get_scale_mask(r_idx);
+        let scale = scale_mask_from_steps(r_idx, scale_steps);
         let score = chord_set.intersection(&scale).count();
         
         if score > max_score {
@@ -180,6 +485,9 @@ fn calculate_tonal_depth(chord_notes: &[u8]) -> (Vec<(i32, &'static str)>, usize
     (candidates, max_score, is_perfect)
 }
 
+// 構成音の「機能」を示すインターバル・ラベル (R / M3 / b9 ...)。
+// これは音名ではなく度数機能の表記なので、enharmonic な調スペリング
+// (spell_pc) の対象外。調が変わっても機能は不変なので意図的に固定する。
 fn get_interval_label(root_idx: u8, target_idx: u8) -> &'static str {
     let diff = (target_idx + 12 - root_idx) % 12;
     match diff {
@@ -188,10 +496,468 @@ fn get_interval_label(root_idx: u8, target_idx: u8) -> &'static str {
     }
 }
 
+// --- 1b. Voicing Solver: フレットボード上の実際の押さえを探す ---
+
+// 手のストレッチ上限 (最高フレットと最低フレットの差の許容値)
+const DEFAULT_HAND_STRETCH: u8 = 4;
+// 探索する最大フレット
+const DEFAULT_MAX_FRET: u8 = 12;
+// 保持する上位ボイシング数。UI もエクスポートも最良の 1 つしか使わない。
+// ノード予算内で見つかった候補をすべて並べ替えた後、この数に切り詰める。
+const MAX_VOICINGS: usize = 8;
+// DFS のノード訪問上限。多弦 (〜12 本) では候補が指数的に増えるため、
+// この回数に達したら打ち切り、それまでの上位を返す (UI を固めない)。
+const VOICING_NODE_BUDGET: u32 = 60_000;
+
+// 1本の弦の押さえ。None はその弦を鳴らさない(ミュート)。
+type StringFret = Option<u8>;
+
+// 探索で得られたボイシング。frets[i] は tuning[i] に対応する。
+#[derive(Clone)]
+struct Voicing {
+    frets: Vec<StringFret>,
+    span: u8,     // 押さえる範囲 (開放弦を除く max - min)
+    lowest: u8,   // ネック上の低さ判定用 (使用フレットの最大値)
+}
+
+// 開放弦の集合から、各構成音を押さえられるボイシングを列挙して上位を返す。
+// 各弦について 0..=max_fret のうち構成音になるフレットを候補とし、
+// 1弦1フレットの組み合わせを DFS で列挙する。ストレッチ超過は枝刈り。
+// required (root/3rd/7th) を含まないボイシングは棄却する。
+fn find_voicings(
+    chord_notes: &[u8],
+    required_pcs: &[u8],
+    tuning: &[u8],
+    max_fret: u8,
+    max_span: u8,
+) -> Vec<Voicing> {
+    if chord_notes.is_empty() || tuning.is_empty() {
+        return Vec::new();
+    }
+    let chord_set: HashSet<u8> = chord_notes.iter().cloned().collect();
+
+    // 弦ごとの候補フレット。低いフレットを先に、ミュート(None)は最後に並べ、
+    // 良いシェイプへ早く到達させる (ノード予算内で上位が埋まりやすい)。
+    let per_string: Vec<Vec<StringFret>> = tuning
+        .iter()
+        .map(|&open| {
+            let mut opts: Vec<StringFret> = Vec::new();
+            for f in 0..=max_fret {
+                if chord_set.contains(&((open + f) % 12)) {
+                    opts.push(Some(f));
+                }
+            }
+            opts.push(None);
+            opts
+        })
+        .collect();
+
+    let search = VoicingSearch {
+        per_string: &per_string,
+        tuning,
+        required_pcs,
+        max_span,
+    };
+    let mut results: Vec<Voicing> = Vec::new();
+    let mut current: Vec<StringFret> = Vec::with_capacity(tuning.len());
+    let mut budget = VOICING_NODE_BUDGET;
+    voicing_dfs(&search, 0, &mut current, &mut results, &mut budget);
+
+    // スパンが狭い順 -> ネック上で低い順 に並べ、上位だけ残す
+    results.sort_by_key(|v| (v.span, v.lowest));
+    results.truncate(MAX_VOICINGS);
+    results
+}
+
+// DFS 全体で不変な探索パラメータ。再帰の引数を減らすためにまとめる。
+struct VoicingSearch<'a> {
+    per_string: &'a [Vec<StringFret>],
+    tuning: &'a [u8],
+    required_pcs: &'a [u8],
+    max_span: u8,
+}
+
+fn voicing_dfs(
+    search: &VoicingSearch,
+    string_idx: usize,
+    current: &mut Vec<StringFret>,
+    out: &mut Vec<Voicing>,
+    budget: &mut u32,
+) {
+    let VoicingSearch {
+        per_string,
+        tuning,
+        required_pcs,
+        max_span,
+    } = *search;
+
+    // ノード予算を使い切ったら打ち切る (多弦での分岐爆発に対する早期終了)
+    if *budget == 0 {
+        return;
+    }
+    *budget -= 1;
+
+    if string_idx == per_string.len() {
+        // 押さえている(開放を除く)フレットからスパンを計算
+        let fretted: Vec<u8> = current.iter().filter_map(|&f| f).filter(|&f| f > 0).collect();
+        let span = match (fretted.iter().min(), fretted.iter().max()) {
+            (Some(&lo), Some(&hi)) => hi - lo,
+            _ => 0,
+        };
+        if span > max_span {
+            return;
+        }
+        // 必須音がすべて含まれているか
+        let sounded: HashSet<u8> = current
+            .iter()
+            .zip(tuning.iter())
+            .filter_map(|(&f, &open)| f.map(|fr| (open + fr) % 12))
+            .collect();
+        if !required_pcs.iter().all(|pc| sounded.contains(pc)) {
+            return;
+        }
+        if sounded.is_empty() {
+            return;
+        }
+        let lowest = current.iter().filter_map(|&f| f).max().unwrap_or(0);
+        out.push(Voicing {
+            frets: current.clone(),
+            span,
+            lowest,
+        });
+        return;
+    }
+
+    for &opt in &per_string[string_idx] {
+        // 枝刈り: 今までの押弦と合わせてスパンが上限を超えるなら切る
+        if let Some(f) = opt {
+            if f > 0 {
+                let prior: Vec<u8> = current.iter().filter_map(|&x| x).filter(|&x| x > 0).collect();
+                let lo = prior.iter().chain([f].iter()).min().cloned().unwrap_or(f);
+                let hi = prior.iter().chain([f].iter()).max().cloned().unwrap_or(f);
+                if hi - lo > max_span {
+                    continue;
+                }
+            }
+        }
+        current.push(opt);
+        voicing_dfs(search, string_idx + 1, current, out, budget);
+        current.pop();
+    }
+}
+
+// キャッシュのキー: (コード文字列, チューニング)。
+type VoicingCacheKey = (String, Vec<u8>);
+
+thread_local! {
+    // ui() が行ごと・再描画ごとに chord_voicings を呼ぶため、同じ入力の
+    // 再計算 (指数的 DFS) をここで回避する。
+    static VOICING_CACHE: RefCell<HashMap<VoicingCacheKey, Vec<Voicing>>> =
+        RefCell::new(HashMap::new());
+}
+
+// コード文字列とチューニングから、上位のボイシングを求める薄いラッパ。
+// 同じ (chord, tuning) はメモ化して再描画のたびの再探索を防ぐ。
+fn chord_voicings(chord_str: &str, tuning: &[u8]) -> Vec<Voicing> {
+    let cache_key = (chord_str.trim().to_string(), tuning.to_vec());
+    if let Some(hit) = VOICING_CACHE.with(|c| c.borrow().get(&cache_key).cloned()) {
+        return hit;
+    }
+    let result = compute_chord_voicings(chord_str, tuning);
+    VOICING_CACHE.with(|c| c.borrow_mut().insert(cache_key, result.clone()));
+    result
+}
+
+// parse_chord_v5 と同じ要領でルート/クオリティを取り出し、必須音を計算する。
+fn compute_chord_voicings(chord_str: &str, tuning: &[u8]) -> Vec<Voicing> {
+    let map = get_note_mapping();
+    let s = chord_str.trim();
+    let symbol = s.split('/').next().unwrap_or("");
+    if symbol.is_empty() {
+        return Vec::new();
+    }
+    let (root_str, quality_str) = split_root_quality(symbol);
+    let root_idx = match map.get(root_str) {
+        Some(&i) => i,
+        None => return Vec::new(),
+    };
+
+    let (intervals, priority) = get_quality_intervals_v2(quality_str);
+    let chord_notes: Vec<u8> = intervals.iter().map(|&i| (root_idx + i) % 12).collect();
+    let required_pcs: Vec<u8> = intervals
+        .iter()
+        .zip(priority.iter())
+        .filter(|(_, &req)| req)
+        .map(|(&i, _)| (root_idx + i) % 12)
+        .collect();
+
+    find_voicings(
+        &chord_notes,
+        &required_pcs,
+        tuning,
+        DEFAULT_MAX_FRET,
+        DEFAULT_HAND_STRETCH,
+    )
+}
+
+// コード構成音を、機能に沿ってスペリングした音名で返す。
+// ルートの綴りは局所キーに合わせ、各構成音は spell_chord_tone で度数綴りにする。
+fn spell_chord_notes(chord_str: &str, local_key_pc: u8) -> Vec<String> {
+    let map = get_note_mapping();
+    let s = chord_str.trim();
+    let symbol = s.split('/').next().unwrap_or("");
+    if symbol.is_empty() {
+        return Vec::new();
+    }
+    let (root_str, quality_str) = split_root_quality(symbol);
+    let root_idx = match map.get(root_str) {
+        Some(&i) => i,
+        None => return Vec::new(),
+    };
+    let root_note = *key_spelling(local_key_pc)
+        .get(&root_idx)
+        .unwrap_or(&default_tonic_note(root_idx));
+
+    let intervals = get_quality_intervals(quality_str);
+    let mut names: Vec<String> = intervals
+        .iter()
+        .map(|&iv| spell_chord_tone(root_note, iv, &intervals).name())
+        .collect();
+
+    // オンコードのベース音は局所キー基準で綴って先頭に添える
+    if let Some(bass_str) = s.split('/').nth(1) {
+        if let Some(&bass_idx) = map.get(bass_str) {
+            names.insert(0, spell_pc(bass_idx, local_key_pc));
+        }
+    }
+    names
+}
+
+// --- 1d. MIDI Output (optional, behind `midi` feature) ---
+// cellseq の MIDI 再生連携にならい、選択中のコードや progression 全体、
+// さらに計算済みボイシングを弦ごとのアルペジオとして鳴らせるようにする。
+#[cfg(feature = "midi")]
+mod midi {
+    use crate::Voicing;
+    use midir::{MidiOutput, MidiOutputConnection};
+    use std::{thread, time::Duration};
+
+    pub struct MidiPlayer {
+        conn: MidiOutputConnection,
+    }
+
+    impl MidiPlayer {
+        // 最初に見つかった MIDI 出力ポートへ接続する。
+        pub fn new() -> anyhow::Result<Self> {
+            let out = MidiOutput::new("open-tuning-analyzer")?;
+            let ports = out.ports();
+            let port = ports
+                .first()
+                .ok_or_else(|| anyhow::anyhow!("no MIDI output port available"))?;
+            let conn = out
+                .connect(port, "otu-out")
+                .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+            Ok(Self { conn })
+        }
+
+        // ピッチクラス集合を root_octave 付近に配置して同時発音する。
+        pub fn play_chord(&mut self, pcs: &[u8], root_octave: u8, bpm: u16) {
+            let dur = Duration::from_millis((60_000 / bpm.max(1) as u64).max(1));
+            let notes = voiced_notes(pcs, root_octave);
+            for &n in &notes {
+                let _ = self.conn.send(&[0x90, n, 80]);
+            }
+            thread::sleep(dur);
+            for &n in &notes {
+                let _ = self.conn.send(&[0x80, n, 0]);
+            }
+        }
+
+        // progression を1コードずつ順に鳴らす。
+        pub fn play_progression(&mut self, chords: &[Vec<u8>], root_octave: u8, bpm: u16) {
+            for pcs in chords {
+                self.play_chord(pcs, root_octave, bpm);
+            }
+        }
+
+        // 計算済みボイシングを弦ごとのアルペジオとして鳴らす。
+        pub fn play_voicing(&mut self, voicing: &Voicing, tuning: &[u8], root_octave: u8, bpm: u16) {
+            let step = Duration::from_millis((15_000 / bpm.max(1) as u64).max(1));
+            for (i, f) in voicing.frets.iter().enumerate() {
+                if let (Some(fr), Some(&open)) = (f, tuning.get(i)) {
+                    let note = (root_octave as u16 * 12 + open as u16 + *fr as u16).min(127) as u8;
+                    let _ = self.conn.send(&[0x90, note, 80]);
+                    thread::sleep(step);
+                    let _ = self.conn.send(&[0x80, note, 0]);
+                }
+            }
+        }
+    }
+
+    // ピッチクラスを root_octave から上昇配置し、MIDI ノート番号の列にする。
+    fn voiced_notes(pcs: &[u8], root_octave: u8) -> Vec<u8> {
+        let base = root_octave as u16 * 12;
+        let mut prev = base;
+        let mut out = Vec::new();
+        for &pc in pcs {
+            let mut n = base + pc as u16;
+            while n < prev {
+                n += 12;
+            }
+            prev = n;
+            out.push(n.min(127) as u8);
+        }
+        out
+    }
+}
+
+// --- 1e. LilyPond Export: progression を楽譜ソースに書き出す ---
+
+// クオリティ -> LilyPond chordmode 修飾子。未知のものはパワーコード相当。
+fn lily_quality(quality: &str) -> &'static str {
+    match quality {
+        "" | "M" | "maj" => "",
+        "m" | "min" | "-" => ":m",
+        "dim" | "o" => ":dim",
+        "aug" | "+" => ":aug",
+        "sus4" | "sus" => ":sus4",
+        "sus2" => ":sus2",
+        "7" | "dom7" => ":7",
+        "M7" | "maj7" | "Maj7" | "jq" => ":maj7",
+        "m7" | "min7" | "-7" => ":m7",
+        "mM7" | "mMaj7" => ":m7+",
+        "dim7" | "o7" => ":dim7",
+        "m7-5" | "m7b5" | "half-dim" | "ø" => ":m7.5-",
+        "6" => ":6",
+        "m6" => ":m6",
+        "9" => ":9",
+        "add9" => ":add9",
+        "M9" | "maj9" => ":maj9",
+        "m9" | "min9" => ":m9",
+        "11" => ":11",
+        "m11" => ":m11",
+        "13" => ":13",
+        "M13" => ":maj13",
+        _ => ":5",
+    }
+}
+
+// ピッチクラスを LilyPond の音名 (オランダ式: cis/des ...) にする。調の綴りを尊重。
+fn lily_note_name(pc: u8, key_pc: u8) -> String {
+    let spelled = spell_pc(pc, key_pc);
+    let mut chars = spelled.chars();
+    let base = chars.next().map(|c| c.to_ascii_lowercase()).unwrap_or('c');
+    let mut out = String::new();
+    out.push(base);
+    for c in chars {
+        match c {
+            '#' => out.push_str("is"),
+            'b' => out.push_str("es"),
+            _ => {}
+        }
+    }
+    out
+}
+
+// 絶対 MIDI 値を LilyPond のオクターブ付き音名にする (c = MIDI 48)。
+fn lily_pitch(midi: u8, key_pc: u8) -> String {
+    let base = lily_note_name(midi % 12, key_pc);
+    let oct = midi as i32 / 12 - 4;
+    let marks = if oct > 0 {
+        "'".repeat(oct as usize)
+    } else {
+        ",".repeat((-oct) as usize)
+    };
+    format!("{}{}", base, marks)
+}
+
+// progression を LilyPond ソースに変換する。
+// lead-sheet 用の \chordmode ブロックと \tabFullNotation のタブ譜を常に出力する。
+// ボイシングが見つからないコードはタブ譜上で全休符 (r1) になる。調に沿った綴りで書き出す。
+fn export_lilypond(progression: &[String], tuning: &[u8], key_pc: u8) -> String {
+    let mut chord_line = String::new();
+    for chord in progression {
+        let (disp, quality, _) = parse_chord_v5(chord);
+        let root = disp.split('/').next().unwrap_or("c");
+        let root_pc = *get_note_mapping().get(root).unwrap_or(&0);
+        chord_line.push_str(&format!(
+            "{}1{} ",
+            lily_note_name(root_pc, key_pc),
+            lily_quality(&quality)
+        ));
+    }
+
+    // 各コードのタブ用和音を組み立てる (計算済みボイシング)
+    let string_count = tuning.len();
+    let mut tab_line = String::new();
+    for chord in progression {
+        match chord_voicings(chord, tuning).into_iter().next() {
+            Some(v) => {
+                let mut notes = Vec::new();
+                for (i, f) in v.frets.iter().enumerate() {
+                    if let (Some(fr), Some(&open)) = (f, tuning.get(i)) {
+                        let midi = 36u16 + open as u16 + *fr as u16; // 低オクターブ基準
+                        let string_num = string_count - i;
+                        notes.push(format!("{}\\{}", lily_pitch(midi.min(127) as u8, key_pc), string_num));
+                    }
+                }
+                if notes.is_empty() {
+                    tab_line.push_str("r1 ");
+                } else {
+                    tab_line.push_str(&format!("<{}>1 ", notes.join(" ")));
+                }
+            }
+            None => tab_line.push_str("r1 "),
+        }
+    }
+
+    format!(
+        "\\version \"2.24.0\"\n\\header {{ title = \"Open Tuning Analyzer Export\" }}\n\n\
+         chordNames = \\chordmode {{\n  {}\n}}\n\n\
+         tabVoicings = {{\n  {}\n}}\n\n\
+         \\score {{\n  <<\n    \\new ChordNames \\chordNames\n    \
+         \\new TabStaff {{\n      \\tabFullNotation\n      \\tabVoicings\n    }}\n  >>\n}}\n",
+        chord_line.trim_end(),
+        tab_line.trim_end()
+    )
+}
+
+// --- 1f. Instrument presets: 弦数可変 & 名前付きチューニング ---
+
+// 名前付きチューニングのプリセット集。循環キーで切り替える。
+fn tuning_presets() -> Vec<(&'static str, &'static str)> {
+    vec![
+        ("Standard", "E A D G B E"),
+        ("Drop D", "D A D G B E"),
+        ("DADGAD", "D A D G A D"),
+        ("Bass EADG", "E A D G"),
+        ("Ukulele GCEA", "G C E A"),
+    ]
+}
+
+// 4〜12 弦のチューニング文字列を検証し、各トークンを音名としてパースする。
+// 不正があれば Err にトークンを含むメッセージを返す (unwrap_or(&0) の無言誤変換を廃止)。
+fn parse_tuning(input: &str) -> Result<Vec<u8>, String> {
+    let tokens: Vec<&str> = input.split_whitespace().collect();
+    if !(4..=12).contains(&tokens.len()) {
+        return Err(format!("need 4-12 strings, got {}", tokens.len()));
+    }
+    let map = get_note_mapping();
+    let mut parsed = Vec::with_capacity(tokens.len());
+    for t in &tokens {
+        match map.get(t) {
+            Some(&i) => parsed.push(i),
+            None => return Err(format!("unknown note: '{}'", t)),
+        }
+    }
+    Ok(parsed)
+}
+
 // --- 2. App State ---
 enum InputMode {
     Chord,
     Tuning,
+    Identify,
 }
 
 struct App {
@@ -201,6 +967,20 @@ struct App {
     tuning: Vec<u8>,
     key: u8,
     input_mode: InputMode,
+    // 逆引きモード: 音名を並べて入力し、コード名候補を得る
+    identify_input: String,
+    identify_result: Vec<String>,
+    // アクティブなスケールファミリ (scale_families() のインデックス)
+    scale_family: usize,
+    // MIDI 再生用: 選択中の行・テンポ(BPM)・ルートオクターブ
+    selected: usize,
+    tempo_bpm: u16,
+    root_octave: u8,
+    // チューニング入力の検証エラー (None = 正常)
+    tuning_error: Option<String>,
+    // 選択中のプリセット (tuning_presets() のインデックス)。None = custom
+    // (どのプリセットにも一致しない手入力チューニング)。
+    preset_idx: Option<usize>,
 }
 
 impl App {
@@ -213,34 +993,105 @@ impl App {
             tuning: vec![0, 7, 2, 7, 9, 2], // C G D G A D
             input_mode: InputMode::Chord,
             key: 0,
+            identify_input: String::new(),
+            identify_result: Vec::new(),
+            scale_family: 0,
+            selected: 0,
+            tempo_bpm: 100,
+            root_octave: 4,
+            tuning_error: None,
+            // 既定の C G D G A D はプリセットに無いので custom 扱い
+            preset_idx: None,
+        }
+    }
+
+    // プリセットを循環させて即適用する (再入力不要)。
+    fn cycle_preset(&mut self, forward: bool) {
+        let presets = tuning_presets();
+        let n = presets.len();
+        let next = match self.preset_idx {
+            Some(i) if forward => (i + 1) % n,
+            Some(i) => (i + n - 1) % n,
+            None if forward => 0,
+            None => n - 1,
+        };
+        self.preset_idx = Some(next);
+        let (_, tuning_str) = presets[next];
+        self.tuning_input = tuning_str.to_string();
+        match parse_tuning(tuning_str) {
+            Ok(t) => {
+                self.tuning = t;
+                self.tuning_error = None;
+            }
+            Err(e) => self.tuning_error = Some(e),
+        }
+    }
+
+    // 選択行を1つ進める/戻す (範囲内でクランプ)
+    fn move_selection(&mut self, forward: bool) {
+        if self.progression.is_empty() {
+            return;
+        }
+        let last = self.progression.len() - 1;
+        if forward {
+            self.selected = (self.selected + 1).min(last);
+        } else {
+            self.selected = self.selected.saturating_sub(1);
         }
     }
 
+    // スケールファミリを循環させる (Left/Right キー用)
+    fn cycle_scale(&mut self, forward: bool) {
+        let n = scale_families().len();
+        self.scale_family = if forward {
+            (self.scale_family + 1) % n
+        } else {
+            (self.scale_family + n - 1) % n
+        };
+    }
+
+    // アクティブなスケールのステップ列
+    fn active_scale_steps(&self) -> Vec<u8> {
+        scale_families()[self.scale_family].1.clone()
+    }
+
     fn submit(&mut self) {
-        // 1. 入力モードの判定
+        // 入力モードごとに確定処理を分岐する
         match self.input_mode {
             InputMode::Chord => {
-                // 2. コード入力モードの場合
                 if !self.input.is_empty() {
                     self.progression = self.input.split_whitespace().map(|s| s.to_string()).collect();
                     self.input.clear();
+                    // 選択行が新しい progression の範囲を超えないようクランプ
+                    self.selected = self.selected.min(self.progression.len().saturating_sub(1));
                 }
-            },
+            }
             InputMode::Tuning => {
-                // 3. チューニング入力モードの場合
-                if !self.tuning_input.is_empty() && self.tuning_input.split_whitespace().count() == 6 {
-                    self.tuning = self.tuning_input.split_whitespace().map(|s| *get_note_mapping().get(s).unwrap_or(&0)).collect();
+                // 4〜12 弦を受け付け、トークンごとに検証する
+                match parse_tuning(&self.tuning_input) {
+                    Ok(t) => {
+                        // 手入力がプリセットに一致すればその名前を、しなければ
+                        // custom (None) を表示し、ラベルの食い違いを防ぐ。
+                        self.preset_idx = tuning_presets()
+                            .iter()
+                            .position(|(_, s)| parse_tuning(s).ok().as_ref() == Some(&t));
+                        self.tuning = t;
+                        self.tuning_error = None;
+                    }
+                    Err(e) => self.tuning_error = Some(e),
                 }
             }
-        }
-
-        if !self.input.is_empty() {
-            self.progression = self.input.split_whitespace().map(|s| s.to_string()).collect();
-            self.input.clear();
-        }
-
-        if !self.tuning_input.is_empty() && self.tuning_input.split_whitespace().count() == 6 {
-            self.tuning = self.tuning_input.split_whitespace().map(|s| *get_note_mapping().get(s).unwrap_or(&0)).collect();
+            InputMode::Identify => {
+                // 音名を並べた入力を逆引きして候補コード名を得る
+                let map = get_note_mapping();
+                let pcs: Vec<u8> = self
+                    .identify_input
+                    .split_whitespace()
+                    .filter_map(|tok| map.get(tok).cloned())
+                    .collect();
+                let bass = pcs.first().cloned();
+                self.identify_result = identify_chord(&pcs, bass, self.key);
+            }
         }
     }
 }
@@ -264,9 +1115,63 @@ fn main() -> Result<()> {
                     KeyCode::Enter => app.submit(),
                     KeyCode::Up => app.key = (app.key + 1) % 12,
                     KeyCode::Down => app.key = (app.key + 11) % 12,
+                    KeyCode::Right => app.cycle_scale(true),
+                    KeyCode::Left => app.cycle_scale(false),
+                    // 行選択 / テンポ / オクターブ (MIDI フッター操作)
+                    KeyCode::F(1) => app.move_selection(false),
+                    KeyCode::F(2) => app.move_selection(true),
+                    KeyCode::F(3) => app.cycle_preset(false),
+                    KeyCode::F(4) => app.cycle_preset(true),
+                    KeyCode::F(9) => app.tempo_bpm = app.tempo_bpm.saturating_sub(5).max(20),
+                    KeyCode::F(10) => app.tempo_bpm = (app.tempo_bpm + 5).min(300),
+                    KeyCode::F(11) => app.root_octave = app.root_octave.saturating_sub(1),
+                    KeyCode::F(12) => app.root_octave = (app.root_octave + 1).min(8),
+                    // F8: 現在の progression を LilyPond ソースに書き出す
+                    KeyCode::F(8) => {
+                        let src = export_lilypond(&app.progression, &app.tuning, app.key);
+                        let _ = std::fs::write("progression.ly", src);
+                    }
                     _ => {}
                 }
 
+                // MIDI 再生 (feature = "midi" 有効時のみ)
+                #[cfg(feature = "midi")]
+                {
+                    match key.code {
+                        // F5: 選択コードを発音
+                        KeyCode::F(5) => {
+                            if let Some(chord) = app.progression.get(app.selected) {
+                                let (_, _, notes) = parse_chord_v5(chord);
+                                if let Ok(mut player) = midi::MidiPlayer::new() {
+                                    player.play_chord(&notes, app.root_octave, app.tempo_bpm);
+                                }
+                            }
+                        }
+                        // F6: progression 全体をステップ再生
+                        KeyCode::F(6) => {
+                            let chords: Vec<Vec<u8>> = app
+                                .progression
+                                .iter()
+                                .map(|c| parse_chord_v5(c).2)
+                                .collect();
+                            if let Ok(mut player) = midi::MidiPlayer::new() {
+                                player.play_progression(&chords, app.root_octave, app.tempo_bpm);
+                            }
+                        }
+                        // F7: 選択コードのボイシングをアルペジオ再生
+                        KeyCode::F(7) => {
+                            if let Some(chord) = app.progression.get(app.selected) {
+                                if let Some(v) = chord_voicings(chord, &app.tuning).into_iter().next() {
+                                    if let Ok(mut player) = midi::MidiPlayer::new() {
+                                        player.play_voicing(&v, &app.tuning, app.root_octave, app.tempo_bpm);
+                                    }
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+
                 match app.input_mode {
                     InputMode::Chord => {
                         match key.code {
@@ -280,6 +1185,14 @@ fn main() -> Result<()> {
                         match key.code {
                             KeyCode::Char(c) => app.tuning_input.push(c),
                             KeyCode::Backspace => { app.tuning_input.pop(); },
+                            KeyCode::Tab => app.input_mode = InputMode::Identify,
+                            _ => {}
+                        }
+                    },
+                    InputMode::Identify => {
+                        match key.code {
+                            KeyCode::Char(c) => app.identify_input.push(c),
+                            KeyCode::Backspace => { app.identify_input.pop(); },
                             KeyCode::Tab => app.input_mode = InputMode::Chord,
                             _ => {}
                         }
@@ -299,7 +1212,7 @@ fn ui(f: &mut Frame, app: &mut App) {
         .constraints([Constraint::Length(3), Constraint::Min(1), Constraint::Length(1)])
         .split(f.size());
 
-    let current_key_name = idx_to_note_name(app.key);
+    let current_key_name = default_tonic_note(app.key).name();
 
     match app.input_mode {
         InputMode::Chord => {
@@ -311,12 +1224,32 @@ fn ui(f: &mut Frame, app: &mut App) {
             f.render_widget(input_p, chunks[0]);
         },
         InputMode::Tuning => {
-            // ★ こちらも同様に
-            let title = format!(" Input Tuning (Key: {}) ", current_key_name);
+            // ★ プリセット名・エラー・弦数を表示 (F3/F4 でプリセット切替)
+            let preset_name = match app.preset_idx {
+                Some(i) => tuning_presets()[i].0,
+                None => "custom",
+            };
+            let title = match &app.tuning_error {
+                Some(e) => format!(" Input Tuning [ERR: {}] ", e),
+                None => format!(" Input Tuning ({} strings, preset: {}) ", app.tuning.len(), preset_name),
+            };
             let input_p = Paragraph::new(app.tuning_input.as_str())
             .block(Block::default().borders(Borders::ALL).title(title))
             .style(Style::default().fg(Color::Cyan));
             f.render_widget(input_p, chunks[0]);
+        },
+        InputMode::Identify => {
+            // ★ 逆引きモード: 入力した音名と推定結果を併記する
+            let title = " Identify Notes (space-separated) -> [Enter] ";
+            let body = if app.identify_result.is_empty() {
+                app.identify_input.clone()
+            } else {
+                format!("{}  =>  {}", app.identify_input, app.identify_result.join(", "))
+            };
+            let input_p = Paragraph::new(body)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .style(Style::default().fg(Color::Cyan));
+            f.render_widget(input_p, chunks[0]);
         }
     }
 
@@ -331,7 +1264,7 @@ fn ui(f: &mut Frame, app: &mut App) {
     // 弦の数に合わせて「6(C), 5(G)...」を生成
     for (i, &note_idx) in app.tuning.iter().enumerate() {
         let string_num = string_count - i;
-        let note_name = idx_to_note_name(note_idx);
+        let note_name = spell_pc(note_idx, app.key);
         let header_label = format!("{}({})", string_num, note_name);
         header_cells.push(Cell::from(header_label).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
     }
@@ -345,13 +1278,14 @@ fn ui(f: &mut Frame, app: &mut App) {
         let parts: Vec<&str> = root_disp.split('/').collect();
         let root_idx = *get_note_mapping().get(parts[0]).unwrap_or(&0);
 
-        // ★ 変更点: 複数の候補を受け取る
-        let (candidates, _score, perfect) = calculate_tonal_depth(&relative_notes);
+        // ★ 変更点: アクティブなスケールファミリで複数候補を評価する
+        let scale_steps = app.active_scale_steps();
+        let (candidates, _score, perfect) = calculate_tonal_depth(&relative_notes, &scale_steps);
         let display_candidates: Vec<(i32, String)> = candidates.iter()
             .map(|&(d, s)| {
                 let rel_key_idx = *get_note_mapping().get(s).unwrap_or(&0);
                 let abs_key_idx = (rel_key_idx + app.key) % 12; // 実際の音階に戻す
-                let abs_key_name = idx_to_note_name(abs_key_idx).to_string();
+                let abs_key_name = default_tonic_note(abs_key_idx).name();
                 (d, abs_key_name)
             })
             .collect();
@@ -359,7 +1293,7 @@ fn ui(f: &mut Frame, app: &mut App) {
         // 代表値（スケール表示用）
         let key_root_name = display_candidates.first().map(|c| c.1.as_str()).unwrap_or("C");
         let key_root_idx = *get_note_mapping().get(key_root_name).unwrap_or(&0);
-        let scale_notes = get_scale_mask(key_root_idx);
+        let scale_notes = scale_mask_from_steps(key_root_idx, &scale_steps);
 
         let mut cells = Vec::new();
         cells.push(Cell::from(chord_str.as_str()).style(Style::default().add_modifier(Modifier::BOLD)));
@@ -387,25 +1321,36 @@ fn ui(f: &mut Frame, app: &mut App) {
         cells.push(Cell::from(key_str));
         // ... (Notes, Strings表示は変更なし) ...
         
-        let note_names: Vec<String> = notes.iter().map(|&i| idx_to_note_name(i).to_string()).collect();
+        // ★ 局所キーに合わせて機能的に正しいスペリングで構成音を表示
+        let note_names = spell_chord_notes(chord_str, key_root_idx);
         cells.push(Cell::from(note_names.join(" ")).style(Style::default().fg(Color::DarkGray)));
 
-        for &t_idx in &app.tuning {
+        // ★ ボイシング探索: 最良のシェイプを 1 つ取り出して各弦の押弦を表示する
+        let best_voicing = chord_voicings(chord_str, &app.tuning).into_iter().next();
+
+        for (si, &t_idx) in app.tuning.iter().enumerate() {
             let interval = get_interval_label(root_idx, t_idx);
             let in_chord = notes.contains(&t_idx);
             let in_scale = scale_notes.contains(&t_idx);
 
-            let (txt, sty) = if in_chord {
+            let (label, sty) = if in_chord {
                 (interval.to_string(), Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))
             } else if in_scale {
                 (interval.to_string(), Style::default().fg(Color::Cyan))
             } else {
                 (format!("X({})", interval), Style::default().fg(Color::Red))
             };
+
+            // インターバル表示の下段にフレット番号(鳴らさない弦は x)を添える
+            let fret_label = match best_voicing.as_ref().and_then(|v| v.frets.get(si)) {
+                Some(Some(f)) => format!("f{}", f),
+                _ => "x".to_string(),
+            };
+            let txt = format!("{}\n{}", label, fret_label);
             cells.push(Cell::from(txt).style(sty));
         }
 
-        Row::new(cells)
+        Row::new(cells).height(2)
     });
 
    let mut constraints = vec![
@@ -425,7 +1370,84 @@ fn ui(f: &mut Frame, app: &mut App) {
 
     f.render_widget(table, chunks[1]);
     
-    let footer = Paragraph::new("Ultra-Lightweight Mode | No ML, No Audio | Esc to Quit")
-        .style(Style::default().fg(Color::DarkGray));
+    let scale_name = scale_families()[app.scale_family].0;
+    let footer = Paragraph::new(format!(
+        "Scale: {} (<-/->) | Sel {}/{} (F1/F2) | {} BPM (F9/F10) | Oct {} (F11/F12) | Play F5/F6/F7 | Esc",
+        scale_name,
+        app.selected + 1,
+        app.progression.len().max(1),
+        app.tempo_bpm,
+        app.root_octave,
+    ))
+    .style(Style::default().fg(Color::DarkGray));
     f.render_widget(footer, chunks[2]);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(letter: u8, accidental: i8) -> Note {
+        Note { letter, accidental }
+    }
+
+    #[test]
+    fn chord_tone_spells_minor_third_as_flat() {
+        // F の短3度 (interval 3) は Ab であって G# ではない
+        assert_eq!(spell_chord_tone(note(3, 0), 3, &[0, 3, 7]).name(), "Ab");
+    }
+
+    #[test]
+    fn chord_tone_spells_sharp_nine_as_sharp() {
+        // C7#9 の #9 (interval 15) は D# であって Eb ではない
+        assert_eq!(spell_chord_tone(note(0, 0), 15, &[0, 4, 7, 10, 15]).name(), "D#");
+    }
+
+    #[test]
+    fn dim7_spells_as_stacked_thirds() {
+        // Dbdim7 は Db-Fb-Abb-Cbb と3度で積む (b5 は Abb、dim7 は Cbb)
+        assert_eq!(spell_chord_notes("Dbdim7", 1), ["Db", "Fb", "Abb", "Cbb"]);
+    }
+
+    #[test]
+    fn major_scale_uses_each_letter_once() {
+        let names: Vec<String> = spell_scale(note(0, 0), &[0, 2, 4, 5, 7, 9, 11])
+            .iter()
+            .map(Note::name)
+            .collect();
+        assert_eq!(names, ["C", "D", "E", "F", "G", "A", "B"]);
+    }
+
+    #[test]
+    fn spell_pc_respects_local_key() {
+        // C キーでは pc1 は慣用スペリングの Db、D キーでは導音の C#
+        assert_eq!(spell_pc(1, 0), "Db");
+        assert_eq!(spell_pc(1, 2), "C#");
+    }
+
+    #[test]
+    fn identify_names_c_major_triad() {
+        // メジャー三和音はリードシート風に "C" (空クオリティ)
+        let names = identify_chord(&[0, 4, 7], None, 0);
+        assert_eq!(names.first().map(String::as_str), Some("C"));
+    }
+
+    #[test]
+    fn voicing_sounds_required_tones() {
+        // 標準チューニングの C メジャーは最低でもルート(C)と3度(E)を鳴らす
+        let standard = [4u8, 9, 2, 7, 11, 4];
+        let best = chord_voicings("C", &standard)
+            .into_iter()
+            .next()
+            .expect("should find at least one C voicing");
+        let sounded: HashSet<u8> = best
+            .frets
+            .iter()
+            .zip(standard.iter())
+            .filter_map(|(&f, &open)| f.map(|fr| (open + fr) % 12))
+            .collect();
+        assert!(sounded.contains(&0), "root C must sound");
+        assert!(sounded.contains(&4), "major third E must sound");
+        assert!(best.span <= DEFAULT_HAND_STRETCH);
+    }
+}